@@ -0,0 +1,326 @@
+//! Reconstructs function names, hashes and counter layout for "lightweight"
+//! instrumentation profiles (built with debug-info correlation enabled, so
+//! the raw profile itself carries only counter values) from the
+//! instrumented binary's DWARF, mirroring LLVM's `InstrProfCorrelator`.
+
+use crate::instrumentation_profile::types::{
+    InstrProfRecord, InstrumentationProfile, NamedInstrProfRecord, Symtab,
+};
+use gimli::{AttributeValue, DwAt};
+use object::{Object, ObjectSection};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// LLVM's DWARF extension attributes used to recover counter layout; see
+/// `InstrProfCorrelator.cpp`'s `IPSK_*` / `DW_AT_LLVM_*` constants.
+///
+/// KNOWN CAVEAT: these are LLVM vendor DWARF extension numbers, not part of
+/// the stable DWARF standard. The tests below build synthetic DWARF (via
+/// `gimli::write`/`object::write`) carrying these exact attribute numbers
+/// and confirm `correlate_profile` recovers the expected record from it —
+/// that proves the DWARF-walking and attribute-decoding logic is internally
+/// consistent, but NOT that these numbers match a real, debug-info-
+/// correlated binary produced by an actual `clang`/`llvm-profdata`, since no
+/// such fixture or tool is available in this environment. If the LLVM
+/// revision that produced a given binary assigns these numbers differently,
+/// `correlate_profile` below will simply find zero matching subprograms and
+/// return `CorrelatorError::NoCorrelatedSubprograms` rather than silently
+/// misreading counter data — but the values themselves should still be
+/// re-checked against `llvm/lib/ProfileData/InstrProfCorrelator.cpp` (or
+/// `llvm/include/llvm/BinaryFormat/Dwarf.def`) for the LLVM version in use
+/// before trusting them on real input.
+///
+/// This module also depends on the external `gimli` and `object` crates;
+/// there is no `Cargo.toml` in this tree to record that dependency, so
+/// wiring it up is left to whoever adds the manifest.
+const DW_AT_LLVM_HASH: DwAt = DwAt(0x3e09);
+const DW_AT_LLVM_COUNTER_BASE_OFFSET: DwAt = DwAt(0x3e0a);
+const DW_AT_LLVM_NUM_COUNTERS: DwAt = DwAt(0x3e0b);
+
+#[derive(Debug)]
+pub enum CorrelatorError {
+    Io(io::Error),
+    Object(object::Error),
+    Dwarf(gimli::Error),
+    Malformed(String),
+    /// Walked the whole binary's DWARF and found no `DW_TAG_subprogram` entry
+    /// carrying all three `DW_AT_LLVM_*` attributes. Most likely cause: the
+    /// binary wasn't built with debug-info correlation enabled, or the
+    /// hard-coded attribute numbers above no longer match this binary's LLVM
+    /// version. Surfaced as an error instead of an empty profile so callers
+    /// don't mistake "nothing correlated" for "correlated, zero functions".
+    NoCorrelatedSubprograms,
+}
+
+impl fmt::Display for CorrelatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Object(e) => write!(f, "{}", e),
+            Self::Dwarf(e) => write!(f, "{}", e),
+            Self::Malformed(msg) => write!(f, "malformed debug info: {}", msg),
+            Self::NoCorrelatedSubprograms => write!(
+                f,
+                "no subprograms with LLVM debug-info-correlation attributes found; \
+                 binary may not have been built with correlation enabled"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CorrelatorError {}
+
+impl From<io::Error> for CorrelatorError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<object::Error> for CorrelatorError {
+    fn from(e: object::Error) -> Self {
+        Self::Object(e)
+    }
+}
+
+impl From<gimli::Error> for CorrelatorError {
+    fn from(e: gimli::Error) -> Self {
+        Self::Dwarf(e)
+    }
+}
+
+/// Rebuild a fully-named [`InstrumentationProfile`] from `raw_counters` (the
+/// flat counter array read out of a name-less `.profraw`) plus the debug
+/// info of the binary that produced it.
+pub fn correlate_profile(
+    raw_counters: &[u64],
+    binary_path: &Path,
+) -> Result<InstrumentationProfile, CorrelatorError> {
+    let binary_data = fs::read(binary_path)?;
+    let object_file = object::File::parse(&*binary_data)?;
+
+    let load_section = |id: gimli::SectionId| -> Result<Cow<[u8]>, gimli::Error> {
+        Ok(object_file
+            .section_by_name(id.name())
+            .and_then(|section| section.uncompressed_data().ok())
+            .unwrap_or(Cow::Borrowed(&[])))
+    };
+    let dwarf = gimli::Dwarf::load(load_section)?;
+
+    let mut profile = InstrumentationProfile::default();
+    let mut symtab = Symtab::default();
+
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+            let Some(hash) = read_u64_attr(&dwarf, &unit, entry, DW_AT_LLVM_HASH)? else {
+                continue;
+            };
+            let Some(counter_base) = read_u64_attr(&dwarf, &unit, entry, DW_AT_LLVM_COUNTER_BASE_OFFSET)? else {
+                continue;
+            };
+            let Some(num_counters) = read_u64_attr(&dwarf, &unit, entry, DW_AT_LLVM_NUM_COUNTERS)? else {
+                continue;
+            };
+            let name = match entry.attr_value(gimli::DW_AT_name)? {
+                Some(attr) => dwarf.attr_string(&unit, attr)?.to_string_lossy().into_owned(),
+                None => continue,
+            };
+
+            let base = counter_base as usize;
+            let count = num_counters as usize;
+            let counts = raw_counters
+                .get(base..base + count)
+                .ok_or_else(|| {
+                    CorrelatorError::Malformed(format!(
+                        "counter range [{}, {}) out of bounds for {}",
+                        base,
+                        base + count,
+                        name
+                    ))
+                })?
+                .to_vec();
+
+            symtab.add_func_name(name.clone());
+            profile.records.push(NamedInstrProfRecord {
+                name: Some(name),
+                hash: Some(hash),
+                record: InstrProfRecord { counts, data: None },
+            });
+        }
+    }
+
+    if profile.records.is_empty() {
+        return Err(CorrelatorError::NoCorrelatedSubprograms);
+    }
+
+    profile.symtab = symtab;
+    Ok(profile)
+}
+
+fn read_u64_attr<R: gimli::Reader>(
+    _dwarf: &gimli::Dwarf<R>,
+    _unit: &gimli::Unit<R>,
+    entry: &gimli::DebuggingInformationEntry<R>,
+    attr: DwAt,
+) -> Result<Option<u64>, CorrelatorError> {
+    let Some(value) = entry.attr_value(attr)? else {
+        return Ok(None);
+    };
+    match value {
+        AttributeValue::Udata(v) => Ok(Some(v)),
+        AttributeValue::Data1(v) => Ok(Some(v as u64)),
+        AttributeValue::Data2(v) => Ok(Some(v as u64)),
+        AttributeValue::Data4(v) => Ok(Some(v as u64)),
+        AttributeValue::Data8(v) => Ok(Some(v)),
+        other => Err(CorrelatorError::Malformed(format!(
+            "unexpected attribute encoding {:?} for {:?}",
+            other, attr
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gimli::write::{
+        Address, AttributeValue as WriteAttributeValue, Dwarf as WriteDwarf, EndianVec,
+        LineProgram, Sections, Unit,
+    };
+    use gimli::{Encoding, Format, LittleEndian};
+    use object::write::{Object as WriteObject, SectionKind};
+    use object::{Architecture, BinaryFormat, Endianness};
+
+    /// Builds a tiny ELF object file whose `.debug_info`/`.debug_abbrev`
+    /// describe one `DW_TAG_subprogram` named `name`, carrying the three
+    /// `DW_AT_LLVM_*` correlation attributes, and writes it to a fresh file
+    /// under the system temp dir. Returns that path; the caller is
+    /// responsible for removing it.
+    ///
+    /// NOTE: this exercises `correlate_profile` against debug info this
+    /// module constructs itself via `gimli::write`/`object::write`, using
+    /// the same `DW_AT_LLVM_*` attribute numbers the production code reads
+    /// — it proves the DWARF-walking logic is internally consistent, not
+    /// that those attribute numbers match a real `clang`-produced,
+    /// debug-info-correlated binary (no such fixture or `llvm-profdata` is
+    /// available in this environment to validate against).
+    fn write_synthetic_correlated_binary(
+        name: &str,
+        hash: u64,
+        counter_base_offset: u64,
+        num_counters: u64,
+    ) -> std::path::PathBuf {
+        let encoding = Encoding {
+            address_size: 8,
+            format: Format::Dwarf32,
+            version: 4,
+        };
+        let mut dwarf = WriteDwarf::default();
+        let mut unit = Unit::new(encoding, LineProgram::none());
+        let root = unit.root();
+        let subprogram = unit.add(root, gimli::DW_TAG_subprogram);
+        let entry = unit.get_mut(subprogram);
+        entry.set(
+            gimli::DW_AT_name,
+            WriteAttributeValue::String(name.as_bytes().to_vec()),
+        );
+        entry.set(gimli::DW_AT_low_pc, WriteAttributeValue::Address(Address::Constant(0)));
+        entry.set(DW_AT_LLVM_HASH, WriteAttributeValue::Udata(hash));
+        entry.set(
+            DW_AT_LLVM_COUNTER_BASE_OFFSET,
+            WriteAttributeValue::Udata(counter_base_offset),
+        );
+        entry.set(DW_AT_LLVM_NUM_COUNTERS, WriteAttributeValue::Udata(num_counters));
+        dwarf.units.add(unit);
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).expect("dwarf sections should serialize");
+
+        let mut obj = WriteObject::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little);
+        sections
+            .for_each(|id, data| -> Result<(), object::write::Error> {
+                let data = data.slice();
+                if data.is_empty() {
+                    return Ok(());
+                }
+                let section_id =
+                    obj.add_section(Vec::new(), id.name().as_bytes().to_vec(), SectionKind::Debug);
+                obj.section_mut(section_id).set_data(data.to_vec(), 1);
+                Ok(())
+            })
+            .expect("writing sections into the object file should succeed");
+
+        let bytes = obj.write().expect("object file should serialize");
+        let path = std::env::temp_dir().join(format!(
+            "llvm_profparser_correlator_test_{}_{}.o",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, bytes).expect("writing the synthetic object file should succeed");
+        path
+    }
+
+    #[test]
+    fn recovers_name_hash_and_counters_from_synthetic_dwarf() {
+        let path = write_synthetic_correlated_binary("my_function", 0xabcd, 2, 3);
+        let raw_counters = vec![0, 0, 10, 20, 30];
+
+        let result = correlate_profile(&raw_counters, &path);
+        fs::remove_file(&path).ok();
+
+        let profile = result.expect("correlate_profile should recover the synthetic subprogram");
+        assert_eq!(profile.records.len(), 1);
+        let record = &profile.records[0];
+        assert_eq!(record.name.as_deref(), Some("my_function"));
+        assert_eq!(record.hash, Some(0xabcd));
+        assert_eq!(record.record.counts, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn errors_when_no_subprogram_carries_correlation_attributes() {
+        let encoding = Encoding {
+            address_size: 8,
+            format: Format::Dwarf32,
+            version: 4,
+        };
+        let mut dwarf = WriteDwarf::default();
+        let unit = Unit::new(encoding, LineProgram::none());
+        dwarf.units.add(unit);
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).expect("dwarf sections should serialize");
+
+        let mut obj = WriteObject::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little);
+        sections
+            .for_each(|id, data| -> Result<(), object::write::Error> {
+                let data = data.slice();
+                if data.is_empty() {
+                    return Ok(());
+                }
+                let section_id =
+                    obj.add_section(Vec::new(), id.name().as_bytes().to_vec(), SectionKind::Debug);
+                obj.section_mut(section_id).set_data(data.to_vec(), 1);
+                Ok(())
+            })
+            .expect("writing sections into the object file should succeed");
+
+        let bytes = obj.write().expect("object file should serialize");
+        let path = std::env::temp_dir().join(format!(
+            "llvm_profparser_correlator_test_{}_empty.o",
+            std::process::id()
+        ));
+        fs::write(&path, bytes).expect("writing the synthetic object file should succeed");
+
+        let result = correlate_profile(&[], &path);
+        fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(CorrelatorError::NoCorrelatedSubprograms)));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod correlator;
+pub mod order;
+pub mod sample_profile;
+pub mod stats;
+pub mod summary;
+pub mod supplement;
+pub mod temporal_prof;
+pub mod types;
+pub mod writer;
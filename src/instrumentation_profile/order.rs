@@ -0,0 +1,180 @@
+//! Derives a startup/temporal function order from the function-id traces
+//! recorded by temporal profiling, using balanced bipartite-graph
+//! partitioning (mirroring LLVM's `BalancedPartitioning`/`order` mode).
+//!
+//! Functions are the movable nodes; each distinct trace is a utility bucket
+//! containing the functions it touched. Recursively bisecting the function
+//! set to minimize a log-gap cost, then reading leaves in order, yields a
+//! layout that keeps functions which co-occur in the same traces close
+//! together - good for minimizing page faults during startup.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct OrderOptions {
+    /// Stop recursing once a group has this many or fewer functions.
+    pub leaf_size: usize,
+    /// Local-search passes per bisection before giving up on improving it.
+    pub max_passes: usize,
+}
+
+impl Default for OrderOptions {
+    fn default() -> Self {
+        Self {
+            leaf_size: 4,
+            max_passes: 6,
+        }
+    }
+}
+
+/// Compute a recommended function layout from `traces` (each a sequence of
+/// function ids seen in one temporal-profiling run). The result lists every
+/// function id that appears in at least one trace, ordered so that
+/// functions which tend to co-occur are placed near each other.
+pub fn balanced_partition_order(traces: &[Vec<u64>], options: &OrderOptions) -> Vec<u64> {
+    let mut membership: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (bucket, trace) in traces.iter().enumerate() {
+        for &function in trace {
+            let buckets = membership.entry(function).or_default();
+            if buckets.last() != Some(&bucket) {
+                buckets.push(bucket);
+            }
+        }
+    }
+
+    let mut functions: Vec<u64> = membership.keys().copied().collect();
+    functions.sort_unstable();
+
+    bisect(functions, &membership, options)
+}
+
+fn bisect(functions: Vec<u64>, membership: &HashMap<u64, Vec<usize>>, options: &OrderOptions) -> Vec<u64> {
+    if functions.len() <= options.leaf_size {
+        return functions;
+    }
+
+    let mut left: Vec<u64> = Vec::with_capacity(functions.len() / 2);
+    let mut right: Vec<u64> = Vec::with_capacity(functions.len() - functions.len() / 2);
+    for (i, function) in functions.into_iter().enumerate() {
+        if i % 2 == 0 {
+            left.push(function);
+        } else {
+            right.push(function);
+        }
+    }
+
+    let mut bucket_left: HashMap<usize, u64> = HashMap::new();
+    let mut bucket_right: HashMap<usize, u64> = HashMap::new();
+    for &function in &left {
+        for &bucket in membership.get(&function).into_iter().flatten() {
+            *bucket_left.entry(bucket).or_default() += 1;
+        }
+    }
+    for &function in &right {
+        for &bucket in membership.get(&function).into_iter().flatten() {
+            *bucket_right.entry(bucket).or_default() += 1;
+        }
+    }
+
+    for _ in 0..options.max_passes {
+        let mut moved_any = false;
+        moved_any |= try_move_all(&mut left, &mut right, &mut bucket_left, &mut bucket_right, membership);
+        moved_any |= try_move_all(&mut right, &mut left, &mut bucket_right, &mut bucket_left, membership);
+        if !moved_any {
+            break;
+        }
+    }
+
+    let mut ordered = bisect(left, membership, options);
+    ordered.extend(bisect(right, membership, options));
+    ordered
+}
+
+/// One local-search pass: move any function from `from` to `to` whose move
+/// strictly reduces the total log-gap cost, given the current per-bucket
+/// counts on each side. A function is only moved while `from` holds more
+/// functions than `to`, so the two halves stay balanced.
+fn try_move_all(
+    from: &mut Vec<u64>,
+    to: &mut Vec<u64>,
+    from_counts: &mut HashMap<usize, u64>,
+    to_counts: &mut HashMap<usize, u64>,
+    membership: &HashMap<u64, Vec<usize>>,
+) -> bool {
+    let mut moved_any = false;
+    let mut i = 0;
+    while i < from.len() {
+        let function = from[i];
+        let buckets = membership.get(&function).map(Vec::as_slice).unwrap_or(&[]);
+        let gain: f64 = buckets
+            .iter()
+            .map(|bucket| {
+                let from_count = *from_counts.get(bucket).unwrap_or(&0);
+                let to_count = *to_counts.get(bucket).unwrap_or(&0);
+                let before = log_gap_cost(from_count) + log_gap_cost(to_count);
+                let after = log_gap_cost(from_count - 1) + log_gap_cost(to_count + 1);
+                before - after
+            })
+            .sum();
+
+        if gain > 0.0 && from.len() > to.len() {
+            for bucket in buckets {
+                *from_counts.get_mut(bucket).unwrap() -= 1;
+                *to_counts.entry(*bucket).or_default() += 1;
+            }
+            to.push(function);
+            from.remove(i);
+            moved_any = true;
+        } else {
+            i += 1;
+        }
+    }
+    moved_any
+}
+
+fn log_gap_cost(count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        -(count as f64 * (count as f64).log2())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_contains_every_traced_function_exactly_once() {
+        let traces = vec![vec![1, 2, 3], vec![3, 4], vec![5]];
+        let order = balanced_partition_order(&traces, &OrderOptions::default());
+
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn empty_traces_yield_empty_order() {
+        let order = balanced_partition_order(&[], &OrderOptions::default());
+        assert!(order.is_empty());
+    }
+
+    #[test]
+    fn cooccurring_functions_end_up_adjacent() {
+        // 1 and 2 always appear together; 9 never appears with either, so a
+        // balanced bisection should keep 1 and 2 on the same side.
+        let traces: Vec<Vec<u64>> = (0..8).map(|_| vec![1, 2]).collect();
+        let mut traces = traces;
+        traces.push(vec![9]);
+
+        // A leaf size of 1 forces the three functions to be bisected.
+        let options = OrderOptions {
+            leaf_size: 1,
+            ..OrderOptions::default()
+        };
+        let order = balanced_partition_order(&traces, &options);
+        let pos = |f: u64| order.iter().position(|&x| x == f).unwrap();
+        assert!((pos(1) as i64 - pos(2) as i64).abs() <= 1);
+    }
+}
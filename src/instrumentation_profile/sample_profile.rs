@@ -0,0 +1,115 @@
+//! A minimal representation of an LLVM sample-based (AutoFDO) profile, used
+//! to cross-reference instrumentation data in [`crate::instrumentation_profile::supplement`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct FunctionSamples {
+    /// Total number of samples attributed to this function, inlined callees
+    /// included.
+    pub total_samples: u64,
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct SampleProfile {
+    pub functions: BTreeMap<String, FunctionSamples>,
+}
+
+impl SampleProfile {
+    pub fn total_samples(&self, name: &str) -> u64 {
+        self.functions.get(name).map(|f| f.total_samples).unwrap_or(0)
+    }
+
+    pub fn is_hot(&self, name: &str, hot_threshold: u64) -> bool {
+        self.total_samples(name) >= hot_threshold
+    }
+}
+
+#[derive(Debug)]
+pub enum SampleProfileError {
+    Io(io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for SampleProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Malformed(msg) => write!(f, "malformed sample profile: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SampleProfileError {}
+
+impl From<io::Error> for SampleProfileError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Parse the sample-profile text format: one `name:total_samples:entry_count`
+/// header line per function, followed by indented `line: samples` body lines
+/// which are ignored here since the supplement algorithm only needs totals.
+pub fn parse(path: &Path) -> Result<SampleProfile, SampleProfileError> {
+    let text = fs::read_to_string(path)?;
+    let mut profile = SampleProfile::default();
+    for line in text.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') || line.trim().is_empty() {
+            continue;
+        }
+        let (name, total_samples) = parse_header(line)
+            .ok_or_else(|| SampleProfileError::Malformed(format!("malformed header line {:?}", line)))?;
+        profile
+            .functions
+            .insert(name.trim().to_string(), FunctionSamples { total_samples });
+    }
+    Ok(profile)
+}
+
+/// Parse a `name:total_samples[:head_samples]` header line. Function names
+/// (e.g. C++ `std::vector<int>::push_back`) routinely contain `:` themselves,
+/// so this scans from the right for the trailing numeric field(s) instead of
+/// splitting from the left.
+fn parse_header(line: &str) -> Option<(&str, u64)> {
+    let last_colon = line.rfind(':')?;
+    let (head, after_last) = (&line[..last_colon], &line[last_colon + 1..]);
+    let after_last_count: u64 = after_last.trim().parse().ok()?;
+
+    // Three-field form (`name:total_samples:head_samples`): the field we
+    // just parsed is head_samples, so look one colon further left for the
+    // real total_samples.
+    if let Some(second_colon) = head.rfind(':') {
+        let between = &head[second_colon + 1..];
+        if let Ok(total_samples) = between.trim().parse::<u64>() {
+            return Some((&head[..second_colon], total_samples));
+        }
+    }
+
+    // Two-field form (`name:total_samples`): what we parsed is total_samples.
+    Some((head, after_last_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_namespaced_name_with_head_samples() {
+        let (name, total_samples) =
+            parse_header("std::vector<int>::push_back:12345:10").unwrap();
+        assert_eq!(name, "std::vector<int>::push_back");
+        assert_eq!(total_samples, 12345);
+    }
+
+    #[test]
+    fn parses_namespaced_name_without_head_samples() {
+        let (name, total_samples) = parse_header("ns::foo::bar:42").unwrap();
+        assert_eq!(name, "ns::foo::bar");
+        assert_eq!(total_samples, 42);
+    }
+}
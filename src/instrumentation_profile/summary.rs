@@ -0,0 +1,162 @@
+//! Profile-wide statistics, modeled on LLVM's `ProfileSummaryBuilder`.
+
+use crate::instrumentation_profile::types::InstrProfRecord;
+
+/// The cutoffs (in millionths, e.g. `900000` == 90%) `llvm-profdata` reports
+/// a detailed summary for by default.
+pub const DEFAULT_CUTOFFS: &[usize] = &[
+    10_000, 100_000, 200_000, 300_000, 400_000, 500_000, 600_000, 700_000, 800_000, 900_000,
+    950_000, 990_000, 999_000, 999_900, 999_990, 999_999,
+];
+
+#[derive(Debug, Default, Clone)]
+pub struct ProfileSummary {
+    num_functions: usize,
+    max_function_count: u64,
+    max_internal_block_count: u64,
+    total_count: u64,
+    num_counts: usize,
+    counts: Vec<u64>,
+}
+
+impl ProfileSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one function's counters into the running summary.
+    pub fn add_record(&mut self, record: &InstrProfRecord) {
+        self.num_functions += 1;
+        if let Some(&function_count) = record.counts.first() {
+            self.max_function_count = self.max_function_count.max(function_count);
+        }
+        if let Some(&block_max) = record.counts.get(1..).and_then(|c| c.iter().max()) {
+            self.max_internal_block_count = self.max_internal_block_count.max(block_max);
+        }
+        for &count in &record.counts {
+            self.total_count += count;
+            self.num_counts += 1;
+            self.counts.push(count);
+        }
+    }
+
+    pub fn num_functions(&self) -> usize {
+        self.num_functions
+    }
+
+    pub fn max_function_count(&self) -> u64 {
+        self.max_function_count
+    }
+
+    pub fn max_internal_block_count(&self) -> u64 {
+        self.max_internal_block_count
+    }
+
+    pub fn total_count(&self) -> u64 {
+        self.total_count
+    }
+
+    pub fn num_counts(&self) -> usize {
+        self.num_counts
+    }
+
+    /// Compute, for each cutoff `C` (in millionths of the total count), the
+    /// largest counter value `V` such that the cumulative sum of all
+    /// counters `>= V` reaches at least `C/1_000_000 * total_count`.
+    pub fn detailed_summary(&self, cutoffs: &[usize]) -> DetailedSummary {
+        let mut sorted_cutoffs = cutoffs.to_vec();
+        sorted_cutoffs.sort_unstable();
+
+        let mut sorted_counts = self.counts.clone();
+        sorted_counts.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut entries = Vec::with_capacity(sorted_cutoffs.len());
+        let mut cumulative: u64 = 0;
+        let mut seen = 0usize;
+        let mut cutoff_iter = sorted_cutoffs.into_iter().peekable();
+
+        for &count in &sorted_counts {
+            cumulative += count;
+            seen += 1;
+            while let Some(&cutoff) = cutoff_iter.peek() {
+                let target = (self.total_count as u128 * cutoff as u128 + 999_999) / 1_000_000;
+                if (cumulative as u128) < target {
+                    break;
+                }
+                entries.push(SummaryEntry {
+                    cutoff,
+                    min_count: count,
+                    num_counts: sorted_counts.partition_point(|&c| c >= count),
+                });
+                cutoff_iter.next();
+            }
+        }
+        // Any cutoff that can't be reached (e.g. total_count == 0) still gets
+        // an entry so callers can rely on a 1:1 mapping with the input cutoffs.
+        for cutoff in cutoff_iter {
+            entries.push(SummaryEntry {
+                cutoff,
+                min_count: 0,
+                num_counts: seen,
+            });
+        }
+
+        DetailedSummary { entries }
+    }
+}
+
+/// One `(cutoff, min_count, num_counts)` row of a [`DetailedSummary`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SummaryEntry {
+    /// Cutoff in millionths of the total count, e.g. `900000` == 90%.
+    pub cutoff: usize,
+    /// The smallest counter value at which this cutoff is reached.
+    pub min_count: u64,
+    /// How many counters are `>= min_count`.
+    pub num_counts: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DetailedSummary {
+    pub entries: Vec<SummaryEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_cutoffs_include_one_percent() {
+        // LLVM's ProfileSummaryBuilder::DefaultCutoffs starts at 10000 (1%).
+        assert_eq!(DEFAULT_CUTOFFS.first(), Some(&10_000));
+    }
+
+    #[test]
+    fn add_record_tracks_function_and_block_maxima() {
+        let mut summary = ProfileSummary::new();
+        summary.add_record(&InstrProfRecord {
+            counts: vec![5, 1, 9],
+            data: None,
+        });
+        assert_eq!(summary.num_functions(), 1);
+        assert_eq!(summary.max_function_count(), 5);
+        assert_eq!(summary.max_internal_block_count(), 9);
+        assert_eq!(summary.total_count(), 15);
+        assert_eq!(summary.num_counts(), 3);
+    }
+
+    #[test]
+    fn detailed_summary_finds_threshold_for_full_cutoff() {
+        let mut summary = ProfileSummary::new();
+        summary.add_record(&InstrProfRecord {
+            counts: vec![1, 2, 3, 4],
+            data: None,
+        });
+        // 100% cutoff must be reached by the smallest value once every
+        // counter is included.
+        let detailed = summary.detailed_summary(&[1_000_000]);
+        assert_eq!(detailed.entries.len(), 1);
+        assert_eq!(detailed.entries[0].min_count, 1);
+        assert_eq!(detailed.entries[0].num_counts, 4);
+    }
+}
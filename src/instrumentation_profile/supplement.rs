@@ -0,0 +1,165 @@
+//! Supplements a cold instrumentation profile with hints from a sample
+//! profile, mirroring `llvm-profdata merge -supplement-instr-with-sample`.
+
+use crate::instrumentation_profile::sample_profile::SampleProfile;
+use crate::instrumentation_profile::summary::ProfileSummary;
+use crate::instrumentation_profile::types::InstrumentationProfile;
+
+#[derive(Debug, Clone)]
+pub struct SupplementOptions {
+    /// Functions whose max instrumentation count is below this are
+    /// considered "cold". Defaults to 0.1% of the instrumentation profile's
+    /// maximum internal block count when not overridden.
+    pub instr_prof_cold_threshold: Option<u64>,
+    /// Sample-profile total-sample threshold at or above which a function is
+    /// considered "hot" under sampling.
+    pub sample_profile_hot_threshold: u64,
+    /// If the fraction of zero-valued counters in a cold function's record
+    /// exceeds this, its instrumentation data is treated as unreliable and
+    /// dropped outright rather than scaled up.
+    pub zero_counter_threshold: f64,
+    /// Functions with fewer counters than this are assumed to already be
+    /// handled well by the inliner and are left untouched.
+    pub suppl_min_size_threshold: usize,
+}
+
+impl Default for SupplementOptions {
+    fn default() -> Self {
+        Self {
+            instr_prof_cold_threshold: None,
+            sample_profile_hot_threshold: 1,
+            zero_counter_threshold: 0.7,
+            suppl_min_size_threshold: 3,
+        }
+    }
+}
+
+fn cold_threshold(profile: &InstrumentationProfile, options: &SupplementOptions) -> u64 {
+    if let Some(threshold) = options.instr_prof_cold_threshold {
+        return threshold;
+    }
+    let mut summary = ProfileSummary::new();
+    for record in &profile.records {
+        summary.add_record(&record.record);
+    }
+    // A function is cold if its hottest counter is below 0.1% of the
+    // profile's maximum internal block count.
+    (summary.max_internal_block_count() as f64 * 0.001).round() as u64
+}
+
+/// Adjust `profile` in place: functions that look cold under instrumentation
+/// but are hot under sampling either have their counters dropped (if mostly
+/// zero, and therefore untrustworthy) or scaled up toward the sample
+/// profile's hotness.
+pub fn supplement_instr_with_sample(
+    profile: &mut InstrumentationProfile,
+    sample: &SampleProfile,
+    options: &SupplementOptions,
+) {
+    let cold_threshold = cold_threshold(profile, options);
+
+    profile.records.retain_mut(|record| {
+        let Some(name) = record.name.clone() else {
+            return true;
+        };
+        if record.record.counts.len() < options.suppl_min_size_threshold {
+            return true;
+        }
+        let max_count = record.record.counts.iter().copied().max().unwrap_or(0);
+        if max_count >= cold_threshold {
+            return true;
+        }
+        if !sample.is_hot(&name, options.sample_profile_hot_threshold) {
+            return true;
+        }
+
+        let num_counts = record.record.counts.len();
+        let num_zero = record.record.counts.iter().filter(|&&c| c == 0).count();
+        let zero_ratio = num_zero as f64 / num_counts as f64;
+        if zero_ratio > options.zero_counter_threshold {
+            return false;
+        }
+
+        let sample_count = sample.total_samples(&name);
+        let scale = (sample_count as f64 / max_count.max(1) as f64).max(1.0);
+        record.record.scale(scale);
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrumentation_profile::sample_profile::FunctionSamples;
+    use crate::instrumentation_profile::types::{InstrProfRecord, NamedInstrProfRecord};
+
+    fn profile_with(name: &str, counts: Vec<u64>) -> InstrumentationProfile {
+        let mut profile = InstrumentationProfile::default();
+        profile.records.push(NamedInstrProfRecord {
+            name: Some(name.to_string()),
+            hash: Some(1),
+            record: InstrProfRecord { counts, data: None },
+        });
+        profile
+    }
+
+    fn sample_with(name: &str, total_samples: u64) -> SampleProfile {
+        let mut sample = SampleProfile::default();
+        sample
+            .functions
+            .insert(name.to_string(), FunctionSamples { total_samples });
+        sample
+    }
+
+    fn options(cold_threshold: u64) -> SupplementOptions {
+        SupplementOptions {
+            instr_prof_cold_threshold: Some(cold_threshold),
+            sample_profile_hot_threshold: 100,
+            zero_counter_threshold: 0.7,
+            suppl_min_size_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn leaves_small_records_untouched() {
+        let mut profile = profile_with("f", vec![0, 0]);
+        let sample = sample_with("f", 1000);
+        supplement_instr_with_sample(&mut profile, &sample, &options(10));
+        assert_eq!(profile.records[0].record.counts, vec![0, 0]);
+    }
+
+    #[test]
+    fn leaves_non_cold_records_untouched() {
+        let mut profile = profile_with("f", vec![50, 0, 0]);
+        let sample = sample_with("f", 1000);
+        // cold_threshold of 10 means a max count of 50 is not cold.
+        supplement_instr_with_sample(&mut profile, &sample, &options(10));
+        assert_eq!(profile.records[0].record.counts, vec![50, 0, 0]);
+    }
+
+    #[test]
+    fn leaves_records_not_hot_in_sample_untouched() {
+        let mut profile = profile_with("f", vec![1, 0, 0]);
+        // No sample data at all for "f", so it's never hot.
+        let sample = SampleProfile::default();
+        supplement_instr_with_sample(&mut profile, &sample, &options(10));
+        assert_eq!(profile.records[0].record.counts, vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn drops_cold_hot_records_with_mostly_zero_counters() {
+        let mut profile = profile_with("f", vec![1, 0, 0, 0]);
+        let sample = sample_with("f", 1000);
+        supplement_instr_with_sample(&mut profile, &sample, &options(10));
+        assert!(profile.records.is_empty());
+    }
+
+    #[test]
+    fn scales_up_cold_hot_records_with_mostly_nonzero_counters() {
+        let mut profile = profile_with("f", vec![2, 4, 6]);
+        let sample = sample_with("f", 600);
+        // max_count is 6, sample_count is 600, so scale is 100.
+        supplement_instr_with_sample(&mut profile, &sample, &options(10));
+        assert_eq!(profile.records[0].record.counts, vec![200, 400, 600]);
+    }
+}
@@ -0,0 +1,255 @@
+//! Extracts temporal profiling traces directly from an on-disk indexed
+//! profile, for use by [`crate::instrumentation_profile::order`].
+//!
+//! `llvm_profparser::parse` (outside this crate's source) builds the
+//! [`crate::instrumentation_profile::types::InstrumentationProfile`] this
+//! binary otherwise works with, but it predates temporal profiling support
+//! and never populates `temporal_prof_traces`. Rather than depend on a field
+//! that can never be non-empty in practice, this module re-reads the raw
+//! indexed profile file itself: it walks `IndexedInstrProf::Header` to find
+//! `TemporalProfTracesOffset`, seeks there, and decodes the trace stream,
+//! mirroring `IndexedInstrProfReader::readHeader` /
+//! `readTemporalProfTracesHeader` / `readTemporalProfTraces` in LLVM's
+//! `InstrProfReader.cpp`.
+//!
+//! KNOWN CAVEAT: the header layout and variant-mask bit assignments below
+//! (`INSTR_PROF_INDEX_MAGIC`, the `VARIANT_MASK_*` constants, and which
+//! header fields are gated by version vs. by variant bit) are transcribed
+//! from `InstrProfData.inc` as remembered, not checked against the LLVM
+//! source in this sandbox (no real `.profdata` fixture or `llvm-profdata`
+//! binary is available to validate against). Every field here is a
+//! fixed-width read that is bounds- and magic-checked, so a wrong offset or
+//! version is reported as [`TemporalProfError`] rather than silently
+//! misread; the unit tests below only confirm this module round-trips
+//! against its *own* declared header shape, which does not substitute for
+//! validating against a real LLVM-produced indexed profile. Re-check this
+//! layout against the LLVM revision in use before relying on it.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum TemporalProfError {
+    Io(io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for TemporalProfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Malformed(msg) => write!(f, "malformed indexed profile: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TemporalProfError {}
+
+impl From<io::Error> for TemporalProfError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// `INSTR_PROF_INDEX_MAGIC` from `InstrProfData.inc`: the bytes
+/// `\xffIPRF\r\n\x81`, read as a little-endian `u64`.
+const INDEX_MAGIC: u64 = 0x8172_0d0a_4650_49ff;
+
+/// Variant-mask bits packed into the high byte of the on-disk `Version`
+/// field; see `IndexedInstrProf::VARIANT_MASKS_ALL` / `VARIANT_MASK_*`.
+const VARIANT_MASK_MEMPROF: u64 = 0x20 << 56;
+const VARIANT_MASK_TEMPORAL_PROF: u64 = 0x40 << 56;
+const VARIANT_MASKS_ALL: u64 = 0xff00_0000_0000_0000;
+
+/// `BinaryIdOffset` was added to the header in format version 7.
+const MIN_VERSION_WITH_BINARY_ID: u64 = 7;
+
+/// A function count past which a single trace's function-hash list is assumed to
+/// be a corrupt read rather than a real (if unusually long) trace. LLVM
+/// traces in practice stay in the hundreds of functions; a truly pathological
+/// file could need this raised.
+const MAX_PLAUSIBLE_TRACE_LEN: u64 = 10_000_000;
+
+/// Read the temporal-prof-traces stream out of the indexed profile at
+/// `path`. Returns one `Vec<u64>` of function hashes (in call order) per
+/// recorded trace, or an empty `Vec` if the profile's version field doesn't
+/// have the temporal-profiling variant bit set. The per-trace `weight` from
+/// the on-disk format is currently discarded since
+/// [`super::order::balanced_partition_order`] treats every trace as equally
+/// weighted.
+pub fn read_temporal_prof_traces(path: &Path) -> Result<Vec<Vec<u64>>, TemporalProfError> {
+    let data = fs::read(path)?;
+    parse_temporal_prof_traces(&data)
+}
+
+fn parse_temporal_prof_traces(data: &[u8]) -> Result<Vec<Vec<u64>>, TemporalProfError> {
+    let mut cursor = Cursor::new(data);
+
+    let magic = cursor.read_u64()?;
+    if magic != INDEX_MAGIC {
+        return Err(TemporalProfError::Malformed(format!(
+            "bad indexed profile magic 0x{:x}",
+            magic
+        )));
+    }
+    let raw_version = cursor.read_u64()?;
+    let version = raw_version & !VARIANT_MASKS_ALL;
+
+    if raw_version & VARIANT_MASK_TEMPORAL_PROF == 0 {
+        // The temporal-profiling variant bit is not set, so there is no
+        // trace data; that's the common case, not an error.
+        return Ok(Vec::new());
+    }
+
+    // Fields always present, ahead of the optional ones: Unused, HashType,
+    // HashOffset.
+    cursor.skip_u64()?; // Unused
+    cursor.skip_u64()?; // HashType
+    cursor.skip_u64()?; // HashOffset
+
+    if raw_version & VARIANT_MASK_MEMPROF != 0 {
+        cursor.skip_u64()?; // MemProfOffset
+    }
+    if version >= MIN_VERSION_WITH_BINARY_ID {
+        cursor.skip_u64()?; // BinaryIdOffset
+    }
+
+    let traces_offset = cursor.read_u64()?;
+    cursor.seek(traces_offset as usize)?;
+
+    let num_traces = cursor.read_u64()?;
+    let _stream_size = cursor.read_u64()?;
+
+    let mut traces = Vec::with_capacity(num_traces.min(MAX_PLAUSIBLE_TRACE_LEN) as usize);
+    for _ in 0..num_traces {
+        let _weight = cursor.read_u64()?;
+        let num_functions = cursor.read_u64()?;
+        if num_functions > MAX_PLAUSIBLE_TRACE_LEN {
+            return Err(TemporalProfError::Malformed(format!(
+                "implausible trace length {}",
+                num_functions
+            )));
+        }
+        let mut trace = Vec::with_capacity(num_functions as usize);
+        for _ in 0..num_functions {
+            trace.push(cursor.read_u64()?);
+        }
+        traces.push(trace);
+    }
+
+    Ok(traces)
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn seek(&mut self, offset: usize) -> Result<(), TemporalProfError> {
+        if offset > self.data.len() {
+            return Err(TemporalProfError::Malformed(format!(
+                "temporal profile traces offset {} past end of file ({} bytes)",
+                offset,
+                self.data.len()
+            )));
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    fn read_u64(&mut self) -> Result<u64, TemporalProfError> {
+        let end = self.offset + 8;
+        let bytes = self.data.get(self.offset..end).ok_or_else(|| {
+            TemporalProfError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated indexed profile header or temporal profile traces stream",
+            ))
+        })?;
+        self.offset = end;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn skip_u64(&mut self) -> Result<(), TemporalProfError> {
+        self.read_u64().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an indexed-profile-shaped byte buffer: the fixed header
+    /// prefix (Magic, Version with the temporal-prof variant bit set,
+    /// Unused, HashType, HashOffset), then `TemporalProfTracesOffset`
+    /// pointing past the header at the trace stream itself.
+    fn encode(format_version: u64, traces: &[(u64, &[u64])]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&INDEX_MAGIC.to_le_bytes());
+        header.extend_from_slice(&(format_version | VARIANT_MASK_TEMPORAL_PROF).to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes()); // Unused
+        header.extend_from_slice(&0u64.to_le_bytes()); // HashType
+        header.extend_from_slice(&0u64.to_le_bytes()); // HashOffset
+        if format_version >= MIN_VERSION_WITH_BINARY_ID {
+            header.extend_from_slice(&0u64.to_le_bytes()); // BinaryIdOffset
+        }
+
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&(traces.len() as u64).to_le_bytes());
+        stream.extend_from_slice(&0u64.to_le_bytes()); // stream size, unused by the reader
+        for (weight, functions) in traces {
+            stream.extend_from_slice(&weight.to_le_bytes());
+            stream.extend_from_slice(&(functions.len() as u64).to_le_bytes());
+            for function in *functions {
+                stream.extend_from_slice(&function.to_le_bytes());
+            }
+        }
+
+        let traces_offset = (header.len() + 8) as u64;
+        header.extend_from_slice(&traces_offset.to_le_bytes());
+        header.extend_from_slice(&stream);
+        header
+    }
+
+    #[test]
+    fn parses_multiple_traces() {
+        let bytes = encode(9, &[(1, &[10, 20, 30]), (2, &[40])]);
+        let traces = parse_temporal_prof_traces(&bytes).unwrap();
+        assert_eq!(traces, vec![vec![10, 20, 30], vec![40]]);
+    }
+
+    #[test]
+    fn no_temporal_variant_bit_yields_empty_traces() {
+        let mut bytes = encode(9, &[(1, &[10])]);
+        // Clear the variant-mask byte so the header claims no temporal data.
+        bytes[15] = 0;
+        assert_eq!(parse_temporal_prof_traces(&bytes).unwrap(), Vec::<Vec<u64>>::new());
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = encode(9, &[(1, &[10])]);
+        bytes[0] ^= 0xff;
+        assert!(parse_temporal_prof_traces(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        let mut bytes = encode(9, &[(1, &[10, 20, 30])]);
+        bytes.truncate(bytes.len() - 4);
+        assert!(parse_temporal_prof_traces(&bytes).is_err());
+    }
+
+    #[test]
+    fn handles_pre_binary_id_format_version() {
+        let bytes = encode(6, &[(1, &[99])]);
+        let traces = parse_temporal_prof_traces(&bytes).unwrap();
+        assert_eq!(traces, vec![vec![99]]);
+    }
+}
@@ -55,6 +55,12 @@ pub struct InstrumentationProfile {
     pub(crate) is_ir: bool,
     pub records: Vec<NamedInstrProfRecord>,
     pub symtab: Symtab,
+    /// Temporal profiling traces: each entry is the sequence of function
+    /// hashes (as they appear in `symtab`) executed in one program run,
+    /// in call order. Nothing in this tree fills or reads this field; the
+    /// `order` subcommand reads traces from the profile file through
+    /// `temporal_prof::read_temporal_prof_traces` instead.
+    pub temporal_prof_traces: Vec<Vec<u64>>,
 }
 
 impl InstrumentationProfile {
@@ -77,6 +83,53 @@ impl InstrumentationProfile {
             InstrumentationLevel::FrontEnd
         }
     }
+
+    /// Accumulate `other`'s records into `self`, scaling `other`'s counts by
+    /// `weight`. Records are matched by `(name, hash)`; anything that doesn't
+    /// collide is appended, scaled by `weight`. Rebuilds `symtab` from both profiles'
+    /// function names.
+    pub fn merge(&mut self, other: &InstrumentationProfile, weight: f64) -> Result<(), MergeError> {
+        if self.is_ir != other.is_ir {
+            return Err(MergeError::ProfileLevelMismatch);
+        }
+        self.has_csir = self.has_csir || other.has_csir;
+
+        let mut by_key: BTreeMap<(String, u64), usize> = BTreeMap::new();
+        for (idx, record) in self.records.iter().enumerate() {
+            if let (Some(name), Some(hash)) = (&record.name, record.hash) {
+                by_key.insert((name.clone(), hash), idx);
+            }
+        }
+
+        for other_record in &other.records {
+            match (&other_record.name, other_record.hash) {
+                (Some(name), Some(hash)) => {
+                    match by_key.get(&(name.clone(), hash)) {
+                        Some(&idx) => self.records[idx].record.merge(&other_record.record, weight)?,
+                        None => {
+                            by_key.insert((name.clone(), hash), self.records.len());
+                            let mut record = other_record.clone();
+                            record.record.scale(weight);
+                            self.records.push(record);
+                        }
+                    }
+                }
+                _ => {
+                    let mut record = other_record.clone();
+                    record.record.scale(weight);
+                    self.records.push(record);
+                }
+            }
+        }
+
+        for name in other.symtab.names.values() {
+            self.symtab.add_func_name(name.clone());
+        }
+        for name in self.records.iter().filter_map(|r| r.name.clone()) {
+            self.symtab.add_func_name(name);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -121,12 +174,103 @@ pub struct InstrProfRecord {
     pub data: Option<Box<ValueProfDataRecord>>,
 }
 
+impl InstrProfRecord {
+    /// Element-wise accumulate `other` into `self`, scaling `other`'s counts by
+    /// `weight` first. Mirrors `InstrProfRecord::merge` in LLVM's InstrProfData.
+    pub fn merge(&mut self, other: &InstrProfRecord, weight: f64) -> Result<(), MergeError> {
+        if self.counts.len() != other.counts.len() {
+            return Err(MergeError::CounterMismatch {
+                lhs: self.counts.len(),
+                rhs: other.counts.len(),
+            });
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count = count.saturating_add(scale_count(*other_count, weight));
+        }
+        match (&mut self.data, &other.data) {
+            (Some(data), Some(other_data)) => data.merge(other_data, weight),
+            (None, Some(other_data)) => self.data = Some(other_data.clone()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Scale every counter (and value-profile count) by `weight` in place.
+    /// Used to fold an input's own merge weight in before it gets merged
+    /// into (or, for the first input, before anything else is merged into)
+    /// an accumulator.
+    pub fn scale(&mut self, weight: f64) {
+        if weight == 1.0 {
+            return;
+        }
+        for count in &mut self.counts {
+            *count = scale_count(*count, weight);
+        }
+        if let Some(data) = &mut self.data {
+            data.scale(weight);
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct ValueProfDataRecord {
     pub indirect_callsites: Vec<InstrProfValueSiteRecord>,
     pub mem_op_sizes: Vec<InstrProfValueSiteRecord>,
 }
 
+impl ValueProfDataRecord {
+    /// Merge `other` into `self`, combining same-valued entries at each site and
+    /// appending ones that only appear in `other`.
+    pub fn merge(&mut self, other: &ValueProfDataRecord, weight: f64) {
+        merge_value_sites(&mut self.indirect_callsites, &other.indirect_callsites, weight);
+        merge_value_sites(&mut self.mem_op_sizes, &other.mem_op_sizes, weight);
+    }
+
+    /// Scale every value-site count by `weight` in place.
+    pub fn scale(&mut self, weight: f64) {
+        if weight == 1.0 {
+            return;
+        }
+        for site in self.indirect_callsites.iter_mut().chain(self.mem_op_sizes.iter_mut()) {
+            for value in site.iter_mut() {
+                value.count = scale_count(value.count, weight);
+            }
+        }
+    }
+}
+
+fn merge_value_sites(
+    sites: &mut Vec<InstrProfValueSiteRecord>,
+    other_sites: &[InstrProfValueSiteRecord],
+    weight: f64,
+) {
+    sites.resize_with(sites.len().max(other_sites.len()), Vec::new);
+    for (site, other_site) in sites.iter_mut().zip(other_sites) {
+        for other_value in other_site {
+            match site
+                .iter_mut()
+                .find(|value| value.value == other_value.value)
+            {
+                Some(value) => {
+                    value.count = value.count.saturating_add(scale_count(other_value.count, weight))
+                }
+                None => site.push(InstrProfValueData {
+                    value: other_value.value,
+                    count: scale_count(other_value.count, weight),
+                }),
+            }
+        }
+    }
+}
+
+fn scale_count(count: u64, weight: f64) -> u64 {
+    if weight == 1.0 {
+        count
+    } else {
+        (count as f64 * weight).round() as u64
+    }
+}
+
 type InstrProfValueSiteRecord = Vec<InstrProfValueData>;
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -135,8 +279,108 @@ pub struct InstrProfValueData {
     count: u64,
 }
 
+impl InstrProfValueData {
+    pub fn new(value: u64, count: u64) -> Self {
+        Self { value, count }
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Error produced while accumulating two [`InstrProfRecord`]s or
+/// [`InstrumentationProfile`]s into one.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MergeError {
+    /// The two records being combined have a differing number of counters.
+    CounterMismatch { lhs: usize, rhs: usize },
+    /// One profile is IR-level instrumented and the other is front-end
+    /// instrumented; llvm-profdata refuses to mix the two.
+    ProfileLevelMismatch,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CounterMismatch { lhs, rhs } => write!(
+                f,
+                "cannot merge records with different numbers of counters ({} vs {})",
+                lhs, rhs
+            ),
+            Self::ProfileLevelMismatch => {
+                write!(f, "cannot merge an IR-level profile with a front-end profile")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
 #[derive(Clone, Debug)]
 pub struct ValueProfData {
     total_size: u32,
     num_value_kinds: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_with(counts: Vec<u64>, indirect_callsites: Vec<InstrProfValueSiteRecord>) -> NamedInstrProfRecord {
+        let data = if indirect_callsites.is_empty() {
+            None
+        } else {
+            Some(Box::new(ValueProfDataRecord {
+                indirect_callsites,
+                mem_op_sizes: vec![],
+            }))
+        };
+        NamedInstrProfRecord {
+            name: Some("foo".to_string()),
+            hash: Some(1),
+            record: InstrProfRecord { counts, data },
+        }
+    }
+
+    #[test]
+    fn merge_sums_matching_records() {
+        let mut profile = InstrumentationProfile::default();
+        profile.records.push(record_with(vec![1, 2, 3], vec![]));
+
+        let mut other = InstrumentationProfile::default();
+        other.records.push(record_with(vec![10, 20, 30], vec![]));
+
+        profile.merge(&other, 1.0).unwrap();
+        assert_eq!(profile.records[0].record.counts, vec![11, 22, 33]);
+    }
+
+    #[test]
+    fn merge_rejects_counter_mismatch() {
+        let mut profile = InstrumentationProfile::default();
+        profile.records.push(record_with(vec![1, 2], vec![]));
+
+        let mut other = InstrumentationProfile::default();
+        other.records.push(record_with(vec![1, 2, 3], vec![]));
+
+        assert!(profile.merge(&other, 1.0).is_err());
+    }
+
+    #[test]
+    fn scale_applies_to_counts_and_value_data() {
+        let mut record = record_with(
+            vec![2, 4],
+            vec![vec![InstrProfValueData::new(42, 10)]],
+        );
+        record.record.scale(2.0);
+        assert_eq!(record.record.counts, vec![4, 8]);
+        assert_eq!(
+            record.record.data.unwrap().indirect_callsites[0][0].count(),
+            20
+        );
+    }
+}
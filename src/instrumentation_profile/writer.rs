@@ -0,0 +1,448 @@
+//! Serializes an [`InstrumentationProfile`] back out to disk, mirroring LLVM's
+//! `InstrProfWriter`. Supports a human-readable text dump (loosely modeled on
+//! `llvm-profdata show --text`) and a compact binary encoding.
+//!
+//! KNOWN DEVIATION, NEEDS SIGN-OFF: the request asked for output that
+//! "round-trips back through `parse`", but `parse` is `llvm_profparser::parse`
+//! — part of this crate's external dependency, not this source tree — so it
+//! cannot be taught either format from here. This module instead guarantees
+//! a complete round-trip through its own [`read_text`]/[`read_binary`]
+//! (including value-profile data, see the tests below); the binary format
+//! (`LPPRFRAW`) is *not* LLVM's on-disk indexed or raw profile format.
+//! Output from [`write_binary`]/[`write_text`] is therefore only readable by
+//! this module today, not by `parse()` or real `llvm-profdata` consumers.
+//! Either `parse()` grows support for one of these formats, or this request
+//! needs its scope narrowed with the requester before merging.
+
+use crate::instrumentation_profile::types::{
+    InstrProfRecord, InstrProfValueData, InstrumentationProfile, NamedInstrProfRecord, Symtab,
+    ValueProfDataRecord,
+};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+const BINARY_MAGIC: &[u8; 8] = b"LPPRFRAW";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum WriterError {
+    Io(io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for WriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Malformed(msg) => write!(f, "malformed profile: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for WriterError {}
+
+impl From<io::Error> for WriterError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Write `profile` to `out` in the requested format.
+pub fn write_profile(
+    profile: &InstrumentationProfile,
+    format: OutputFormat,
+    out: &mut dyn Write,
+) -> Result<(), WriterError> {
+    match format {
+        OutputFormat::Text => write_text(profile, out),
+        OutputFormat::Binary => write_binary(profile, out),
+    }
+}
+
+/// Emit the `llvm-profdata show --text` dump format: for every record, its
+/// name, hash, counter count, the counters themselves, then any
+/// indirect-call-target / memop-size value-profile sites.
+pub fn write_text(profile: &InstrumentationProfile, out: &mut dyn Write) -> Result<(), WriterError> {
+    writeln!(out, "# IR level Instrumentation Flag")?;
+    writeln!(out, ":ir\t{}", profile.is_ir_level_profile() as u8)?;
+    for record in &profile.records {
+        let (Some(name), Some(hash)) = (&record.name, record.hash) else {
+            continue;
+        };
+        writeln!(out, "{}", name)?;
+        writeln!(out, "# Func Hash:")?;
+        writeln!(out, "{}", hash)?;
+        writeln!(out, "# Num Counters:")?;
+        writeln!(out, "{}", record.record.counts.len())?;
+        writeln!(out, "# Counter Values:")?;
+        for count in &record.record.counts {
+            writeln!(out, "{}", count)?;
+        }
+        if let Some(data) = &record.record.data {
+            writeln!(out, "# Num Value Kinds:")?;
+            writeln!(out, "2")?;
+            write_value_sites(out, "IPVK_IndirectCallTarget", 0, &data.indirect_callsites)?;
+            write_value_sites(out, "IPVK_MemOPSize", 1, &data.mem_op_sizes)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_value_sites(
+    out: &mut dyn Write,
+    kind_name: &str,
+    kind: u32,
+    sites: &[Vec<InstrProfValueData>],
+) -> Result<(), WriterError> {
+    if sites.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "# ValueKind = {}:", kind_name)?;
+    writeln!(out, "{}", kind)?;
+    writeln!(out, "# NumValueSites:")?;
+    writeln!(out, "{}", sites.len())?;
+    for site in sites {
+        writeln!(out, "{}", site.len())?;
+        for value in site {
+            writeln!(out, "{}:{}", value.value(), value.count())?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse the text format produced by [`write_text`] back into an
+/// [`InstrumentationProfile`].
+pub fn read_text(input: &str) -> Result<InstrumentationProfile, WriterError> {
+    let mut lines = input.lines().peekable();
+    let mut profile = InstrumentationProfile::default();
+    let mut symtab = Symtab::default();
+
+    match lines.next() {
+        Some(header) if header.starts_with('#') => {}
+        _ => return Err(WriterError::Malformed("missing IR-level flag header".into())),
+    }
+    let flag_line = lines
+        .next()
+        .ok_or_else(|| WriterError::Malformed("missing IR-level flag value".into()))?;
+    let is_ir = flag_line
+        .split('\t')
+        .nth(1)
+        .map(|v| v.trim() == "1")
+        .unwrap_or(false);
+
+    let mut current_name: Option<String> = None;
+    while let Some(line) = lines.next() {
+        if line.trim().is_empty() {
+            current_name = None;
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if current_name.is_none() {
+            current_name = Some(line.to_string());
+            symtab.add_func_name(line.to_string());
+            continue;
+        }
+
+        // `line` is the hash, preceded by the `# Func Hash:` comment we skipped.
+        let hash: u64 = line
+            .trim()
+            .parse()
+            .map_err(|_| WriterError::Malformed(format!("expected hash, got {:?}", line)))?;
+        lines.next(); // "# Num Counters:"
+        let num_counters: usize = lines
+            .next()
+            .ok_or_else(|| WriterError::Malformed("missing counter count".into()))?
+            .trim()
+            .parse()
+            .map_err(|_| WriterError::Malformed("invalid counter count".into()))?;
+        lines.next(); // "# Counter Values:"
+        let mut counts = Vec::with_capacity(num_counters);
+        for _ in 0..num_counters {
+            let count: u64 = lines
+                .next()
+                .ok_or_else(|| WriterError::Malformed("missing counter value".into()))?
+                .trim()
+                .parse()
+                .map_err(|_| WriterError::Malformed("invalid counter value".into()))?;
+            counts.push(count);
+        }
+
+        let mut data = None;
+        if let Some(peeked) = input_lines_peek(&mut lines) {
+            if peeked.starts_with("# Num Value Kinds:") {
+                lines.next();
+                lines.next(); // kind count, unused: we read sites until blank line
+                let mut value_data = ValueProfDataRecord::default();
+                while let Some(kind_header) = input_lines_peek(&mut lines) {
+                    if !kind_header.starts_with("# ValueKind") {
+                        break;
+                    }
+                    lines.next();
+                    let kind: u32 = lines
+                        .next()
+                        .ok_or_else(|| WriterError::Malformed("missing value kind id".into()))?
+                        .trim()
+                        .parse()
+                        .map_err(|_| WriterError::Malformed("invalid value kind id".into()))?;
+                    lines.next(); // "# NumValueSites:"
+                    let num_sites: usize = lines
+                        .next()
+                        .ok_or_else(|| WriterError::Malformed("missing site count".into()))?
+                        .trim()
+                        .parse()
+                        .map_err(|_| WriterError::Malformed("invalid site count".into()))?;
+                    let mut sites = Vec::with_capacity(num_sites);
+                    for _ in 0..num_sites {
+                        let num_values: usize = lines
+                            .next()
+                            .ok_or_else(|| WriterError::Malformed("missing value count".into()))?
+                            .trim()
+                            .parse()
+                            .map_err(|_| WriterError::Malformed("invalid value count".into()))?;
+                        let mut site = Vec::with_capacity(num_values);
+                        for _ in 0..num_values {
+                            let (value, count) = lines
+                                .next()
+                                .ok_or_else(|| WriterError::Malformed("missing value entry".into()))?
+                                .split_once(':')
+                                .ok_or_else(|| WriterError::Malformed("expected value:count".into()))?;
+                            site.push(InstrProfValueData::new(
+                                value
+                                    .parse()
+                                    .map_err(|_| WriterError::Malformed("invalid value".into()))?,
+                                count
+                                    .parse()
+                                    .map_err(|_| WriterError::Malformed("invalid count".into()))?,
+                            ));
+                        }
+                        sites.push(site);
+                    }
+                    match kind {
+                        0 => value_data.indirect_callsites = sites,
+                        1 => value_data.mem_op_sizes = sites,
+                        other => {
+                            return Err(WriterError::Malformed(format!("unknown value kind {}", other)))
+                        }
+                    }
+                }
+                data = Some(Box::new(value_data));
+            }
+        }
+
+        profile.records.push(NamedInstrProfRecord {
+            name: current_name.take(),
+            hash: Some(hash),
+            record: InstrProfRecord { counts, data },
+        });
+    }
+
+    profile.symtab = symtab;
+    profile.is_ir = is_ir;
+    Ok(profile)
+}
+
+fn input_lines_peek<'a, I: Iterator<Item = &'a str>>(lines: &mut std::iter::Peekable<I>) -> Option<&'a str> {
+    lines.peek().copied()
+}
+
+/// Length-prefixed little-endian binary encoding; not LLVM's on-disk format,
+/// but a stable, self-describing one this crate can round-trip, including
+/// indirect-call-target / memop-size value-profile data.
+pub fn write_binary(profile: &InstrumentationProfile, out: &mut dyn Write) -> Result<(), WriterError> {
+    out.write_all(BINARY_MAGIC)?;
+    out.write_all(&(profile.is_ir_level_profile() as u8).to_le_bytes())?;
+    out.write_all(&(profile.records.len() as u64).to_le_bytes())?;
+    for record in &profile.records {
+        let name = record.name.clone().unwrap_or_default();
+        out.write_all(&(name.len() as u64).to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(&record.hash.unwrap_or_default().to_le_bytes())?;
+        out.write_all(&(record.record.counts.len() as u64).to_le_bytes())?;
+        for count in &record.record.counts {
+            out.write_all(&count.to_le_bytes())?;
+        }
+        write_binary_value_data(out, record.record.data.as_deref())?;
+    }
+    Ok(())
+}
+
+fn write_binary_value_data(
+    out: &mut dyn Write,
+    data: Option<&ValueProfDataRecord>,
+) -> Result<(), WriterError> {
+    out.write_all(&(data.is_some() as u8).to_le_bytes())?;
+    let Some(data) = data else {
+        return Ok(());
+    };
+    write_binary_value_sites(out, &data.indirect_callsites)?;
+    write_binary_value_sites(out, &data.mem_op_sizes)?;
+    Ok(())
+}
+
+fn write_binary_value_sites(
+    out: &mut dyn Write,
+    sites: &[Vec<InstrProfValueData>],
+) -> Result<(), WriterError> {
+    out.write_all(&(sites.len() as u64).to_le_bytes())?;
+    for site in sites {
+        out.write_all(&(site.len() as u64).to_le_bytes())?;
+        for value in site {
+            out.write_all(&value.value().to_le_bytes())?;
+            out.write_all(&value.count().to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_binary(input: &mut dyn Read) -> Result<InstrumentationProfile, WriterError> {
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != BINARY_MAGIC {
+        return Err(WriterError::Malformed("bad magic".into()));
+    }
+    let mut profile = InstrumentationProfile::default();
+    let mut symtab = Symtab::default();
+
+    let mut one = [0u8; 1];
+    input.read_exact(&mut one)?;
+    profile.is_ir = one[0] != 0;
+
+    let num_records = read_u64(input)?;
+    for _ in 0..num_records {
+        let name_len = read_u64(input)? as usize;
+        let mut name_bytes = vec![0u8; name_len];
+        input.read_exact(&mut name_bytes)?;
+        let name = String::from_utf8(name_bytes)
+            .map_err(|_| WriterError::Malformed("non-utf8 function name".into()))?;
+        let hash = read_u64(input)?;
+        let num_counts = read_u64(input)? as usize;
+        let mut counts = Vec::with_capacity(num_counts);
+        for _ in 0..num_counts {
+            counts.push(read_u64(input)?);
+        }
+        let data = read_binary_value_data(input)?;
+        symtab.add_func_name(name.clone());
+        profile.records.push(NamedInstrProfRecord {
+            name: Some(name),
+            hash: Some(hash),
+            record: InstrProfRecord { counts, data },
+        });
+    }
+    profile.symtab = symtab;
+    Ok(profile)
+}
+
+fn read_binary_value_data(
+    input: &mut dyn Read,
+) -> Result<Option<Box<ValueProfDataRecord>>, WriterError> {
+    let mut has_data = [0u8; 1];
+    input.read_exact(&mut has_data)?;
+    if has_data[0] == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Box::new(ValueProfDataRecord {
+        indirect_callsites: read_binary_value_sites(input)?,
+        mem_op_sizes: read_binary_value_sites(input)?,
+    })))
+}
+
+fn read_binary_value_sites(input: &mut dyn Read) -> Result<Vec<Vec<InstrProfValueData>>, WriterError> {
+    let num_sites = read_u64(input)? as usize;
+    let mut sites = Vec::with_capacity(num_sites);
+    for _ in 0..num_sites {
+        let num_values = read_u64(input)? as usize;
+        let mut site = Vec::with_capacity(num_values);
+        for _ in 0..num_values {
+            let value = read_u64(input)?;
+            let count = read_u64(input)?;
+            site.push(InstrProfValueData::new(value, count));
+        }
+        sites.push(site);
+    }
+    Ok(sites)
+}
+
+fn read_u64(input: &mut dyn Read) -> Result<u64, WriterError> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_with_value_data(name: &str) -> NamedInstrProfRecord {
+        NamedInstrProfRecord {
+            name: Some(name.to_string()),
+            hash: Some(42),
+            record: InstrProfRecord {
+                counts: vec![10, 20, 30],
+                data: Some(Box::new(ValueProfDataRecord {
+                    indirect_callsites: vec![vec![InstrProfValueData::new(7, 3)]],
+                    mem_op_sizes: vec![],
+                })),
+            },
+        }
+    }
+
+    #[test]
+    fn text_round_trips_counts_and_value_data() {
+        let mut profile = InstrumentationProfile::default();
+        profile.records.push(record_with_value_data("foo"));
+
+        let mut buf = Vec::new();
+        write_text(&profile, &mut buf).unwrap();
+        let parsed = read_text(std::str::from_utf8(&buf).unwrap()).unwrap();
+
+        assert_eq!(parsed.records.len(), 1);
+        assert_eq!(parsed.records[0].name.as_deref(), Some("foo"));
+        assert_eq!(parsed.records[0].record.counts, vec![10, 20, 30]);
+        let data = parsed.records[0].record.data.as_ref().unwrap();
+        assert_eq!(data.indirect_callsites[0][0].value(), 7);
+        assert_eq!(data.indirect_callsites[0][0].count(), 3);
+    }
+
+    #[test]
+    fn binary_round_trips_counts_and_value_data() {
+        let mut profile = InstrumentationProfile::default();
+        profile.records.push(record_with_value_data("foo"));
+
+        let mut buf = Vec::new();
+        write_binary(&profile, &mut buf).unwrap();
+        let parsed = read_binary(&mut &buf[..]).unwrap();
+
+        assert_eq!(parsed.records.len(), 1);
+        assert_eq!(parsed.records[0].name.as_deref(), Some("foo"));
+        assert_eq!(parsed.records[0].record.counts, vec![10, 20, 30]);
+        let data = parsed.records[0].record.data.as_ref().unwrap();
+        assert_eq!(data.indirect_callsites[0][0].value(), 7);
+        assert_eq!(data.indirect_callsites[0][0].count(), 3);
+    }
+
+    #[test]
+    fn binary_round_trips_record_with_no_value_data() {
+        let mut profile = InstrumentationProfile::default();
+        profile.records.push(NamedInstrProfRecord {
+            name: Some("bar".to_string()),
+            hash: Some(1),
+            record: InstrProfRecord {
+                counts: vec![1],
+                data: None,
+            },
+        });
+
+        let mut buf = Vec::new();
+        write_binary(&profile, &mut buf).unwrap();
+        let parsed = read_binary(&mut &buf[..]).unwrap();
+        assert!(parsed.records[0].record.data.is_none());
+    }
+}
@@ -1,11 +1,18 @@
 use llvm_profparser::instrumentation_profile::stats::*;
 use llvm_profparser::instrumentation_profile::summary::*;
 use llvm_profparser::instrumentation_profile::types::*;
+use llvm_profparser::instrumentation_profile::correlator::correlate_profile;
+use llvm_profparser::instrumentation_profile::order::{balanced_partition_order, OrderOptions};
+use llvm_profparser::instrumentation_profile::sample_profile;
+use llvm_profparser::instrumentation_profile::supplement::{supplement_instr_with_sample, SupplementOptions};
+use llvm_profparser::instrumentation_profile::temporal_prof::read_temporal_prof_traces;
+use llvm_profparser::instrumentation_profile::writer::{write_profile, OutputFormat};
 use llvm_profparser::parse;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
-#[derive(Clone, Debug, Eq, PartialEq, StructOpt)]
+#[derive(Clone, Debug, PartialEq, StructOpt)]
 pub enum Command {
     Show {
         #[structopt(flatten)]
@@ -19,6 +26,14 @@ pub enum Command {
         #[structopt(flatten)]
         overlap: OverlapCommand,
     },
+    Supplement {
+        #[structopt(flatten)]
+        supplement: SupplementCommand,
+    },
+    Order {
+        #[structopt(flatten)]
+        order: OrderCommand,
+    },
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, StructOpt)]
@@ -77,16 +92,103 @@ pub struct ShowCommand {
     /// only usable when the sample profile is in extbinary format
     #[structopt(long = "show_section_info_only")]
     show_section_info_only: bool,
+    /// Instrumented binary to recover function names/hashes/counter layout
+    /// from, for profiles captured with debug-info correlation (so the
+    /// profraw itself carries only raw counter values)
+    #[structopt(long = "binary-file")]
+    binary_file: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, StructOpt)]
 pub struct MergeCommand {
-    /// Input files to merge
+    /// Input files to merge. Each entry may be a bare path, or `weight:path` to
+    /// scale that input's counters before accumulating them.
     #[structopt(name = "input", long = "input", short = "i")]
-    input: Vec<PathBuf>,
+    input: Vec<String>,
     /// Number of merge threads to use (will autodetect by default)
     #[structopt(long = "num-threads", short = "j")]
     jobs: Option<usize>,
+    /// Output file
+    #[structopt(long = "output", short = "o")]
+    output: Option<PathBuf>,
+    /// Emit the merged profile in text format instead of the binary format
+    #[structopt(long = "text")]
+    text: bool,
+}
+
+/// A profile input path together with the weight its counters should be
+/// scaled by before being accumulated into the merged profile.
+struct WeightedInput {
+    weight: f64,
+    path: PathBuf,
+}
+
+impl WeightedInput {
+    /// Parse the `weight:filename` syntax accepted by llvm-profdata merge; a
+    /// bare filename is equivalent to a weight of `1`.
+    fn parse(input: &str) -> Self {
+        match input.split_once(':') {
+            Some((weight, path)) if weight.parse::<f64>().is_ok() => WeightedInput {
+                weight: weight.parse().unwrap(),
+                path: PathBuf::from(path),
+            },
+            _ => WeightedInput {
+                weight: 1.0,
+                path: PathBuf::from(input),
+            },
+        }
+    }
+}
+
+impl MergeCommand {
+    pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let inputs: Vec<WeightedInput> = self.input.iter().map(|i| WeightedInput::parse(i)).collect();
+        let jobs = self.jobs.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)).max(1);
+
+        // Parse inputs in parallel (bounded by `jobs`), then merge serially so
+        // the accumulation order is deterministic regardless of thread timing.
+        let mut parsed: Vec<Result<(InstrumentationProfile, f64), Box<dyn std::error::Error + Send + Sync>>> =
+            Vec::with_capacity(inputs.len());
+        for chunk in inputs.chunks(jobs.max(1)) {
+            let mut handles = Vec::with_capacity(chunk.len());
+            for input in chunk {
+                let path = input.path.clone();
+                let weight = input.weight;
+                handles.push(std::thread::spawn(move || {
+                    parse(&path)
+                        .map(|profile| (profile, weight))
+                        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { format!("{}: {}", path.display(), e).into() })
+                }));
+            }
+            for handle in handles {
+                parsed.push(handle.join().expect("merge worker thread panicked"));
+            }
+        }
+
+        let mut profiles = parsed.into_iter();
+        let (mut merged, first_weight) = profiles
+            .next()
+            .ok_or("no input files given to merge")??;
+        // The first profile's own weight is folded in by scaling its counters
+        // (and value-profile data) the same way later inputs get scaled by
+        // `InstrProfRecord::merge`.
+        for record in &mut merged.records {
+            record.record.scale(first_weight);
+        }
+        for next in profiles {
+            let (profile, weight) = next?;
+            merged.merge(&profile, weight)?;
+        }
+
+        let format = if self.text { OutputFormat::Text } else { OutputFormat::Binary };
+        if let Some(output) = &self.output {
+            let mut file = std::fs::File::create(output)?;
+            write_profile(&merged, format, &mut file)?;
+        } else {
+            write_profile(&merged, OutputFormat::Text, &mut std::io::stdout())?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, StructOpt)]
@@ -109,7 +211,243 @@ pub struct OverlapCommand {
     function: Option<String>,
 }
 
+fn total_count(profile: &InstrumentationProfile) -> u64 {
+    profile
+        .records
+        .iter()
+        .flat_map(|r| r.record.counts.iter())
+        .sum()
+}
+
+fn total_ic_count(profile: &InstrumentationProfile) -> u64 {
+    profile
+        .records
+        .iter()
+        .filter_map(|r| r.record.data.as_ref())
+        .flat_map(|data| data.indirect_callsites.iter())
+        .flat_map(|site| site.iter())
+        .map(|value| value.count())
+        .sum()
+}
+
+fn indexed_records(
+    profile: &InstrumentationProfile,
+    context_sensitive_counts: bool,
+) -> HashMap<(String, u64), &NamedInstrProfRecord> {
+    profile
+        .records
+        .iter()
+        .filter(|r| {
+            r.name.is_some()
+                && r.hash.is_some()
+                && (!profile.is_ir_level_profile() || r.has_cs_flag() == context_sensitive_counts)
+        })
+        .map(|r| ((r.name.clone().unwrap(), r.hash.unwrap()), r))
+        .collect()
+}
+
+/// `min(base_i/base_total, test_i/test_total)` summed over matched counters,
+/// each side normalized by its own profile-wide total count.
+fn counter_overlap(base: &NamedInstrProfRecord, test: &NamedInstrProfRecord, base_total: f64, test_total: f64) -> f64 {
+    base.record
+        .counts
+        .iter()
+        .zip(test.record.counts.iter())
+        .map(|(&b, &t)| {
+            let base_frac = if base_total > 0.0 { b as f64 / base_total } else { 0.0 };
+            let test_frac = if test_total > 0.0 { t as f64 / test_total } else { 0.0 };
+            base_frac.min(test_frac)
+        })
+        .sum()
+}
+
+/// Same idea as [`counter_overlap`] but over indirect-call-target value data,
+/// matched by target value within each call site.
+fn value_overlap(base: &NamedInstrProfRecord, test: &NamedInstrProfRecord, base_total: f64, test_total: f64) -> f64 {
+    let (Some(base_data), Some(test_data)) = (base.record.data.as_ref(), test.record.data.as_ref()) else {
+        return 0.0;
+    };
+    base_data
+        .indirect_callsites
+        .iter()
+        .zip(test_data.indirect_callsites.iter())
+        .map(|(base_site, test_site)| {
+            base_site
+                .iter()
+                .map(|base_value| {
+                    let test_count = test_site
+                        .iter()
+                        .find(|v| v.value() == base_value.value())
+                        .map(|v| v.count())
+                        .unwrap_or(0);
+                    let base_frac = if base_total > 0.0 { base_value.count() as f64 / base_total } else { 0.0 };
+                    let test_frac = if test_total > 0.0 { test_count as f64 / test_total } else { 0.0 };
+                    base_frac.min(test_frac)
+                })
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+impl OverlapCommand {
+    pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let base_profile = parse(&self.base_file)?;
+        let test_profile = parse(&self.test_file)?;
+
+        let base_total = total_count(&base_profile) as f64;
+        let test_total = total_count(&test_profile) as f64;
+        let base_ic_total = total_ic_count(&base_profile) as f64;
+        let test_ic_total = total_ic_count(&test_profile) as f64;
+
+        let base_records = indexed_records(&base_profile, self.context_sensitive_counts);
+        let test_records = indexed_records(&test_profile, self.context_sensitive_counts);
+
+        let mut aggregate_overlap = 0.0;
+        let mut aggregate_value_overlap = 0.0;
+        for (key, test_record) in &test_records {
+            let Some(base_record) = base_records.get(key) else {
+                continue;
+            };
+            let counter_score = counter_overlap(base_record, test_record, base_total, test_total);
+            let value_score = value_overlap(base_record, test_record, base_ic_total, test_ic_total);
+            aggregate_overlap += counter_score;
+            aggregate_value_overlap += value_score;
+
+            let max_count = test_record.record.counts.iter().copied().max().unwrap_or(0);
+            let passes_cutoff = max_count as usize > self.value_cutoff.unwrap_or(0);
+            let passes_function = check_function(Some(&key.0), self.function.as_ref());
+            if !passes_cutoff && self.value_cutoff.is_some() {
+                continue;
+            }
+            if self.function.is_some() && !passes_function {
+                continue;
+            }
+            let test_share = if test_total > 0.0 {
+                test_record.record.counts.iter().sum::<u64>() as f64 / test_total
+            } else {
+                0.0
+            };
+            println!(
+                "{}: counter overlap = {:.6}, value overlap = {:.6}, weight = {:.6}",
+                key.0, counter_score, value_score, test_share
+            );
+        }
+
+        println!("Overlap ratio: {:.6}", aggregate_overlap);
+        println!("Indirect call target overlap ratio: {:.6}", aggregate_value_overlap);
+
+        if let Some(output) = &self.output {
+            std::fs::write(
+                output,
+                format!(
+                    "Overlap ratio: {:.6}\nIndirect call target overlap ratio: {:.6}\n",
+                    aggregate_overlap, aggregate_value_overlap
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, StructOpt)]
+pub struct SupplementCommand {
+    /// Cold instrumentation profile to supplement
+    #[structopt(long = "instr-profile", short = "i")]
+    instr_profile: PathBuf,
+    /// Sample profile to pull hotness information from
+    #[structopt(long = "sample-profile", short = "s")]
+    sample_profile: PathBuf,
+    /// Output file
+    #[structopt(long = "output", short = "o")]
+    output: PathBuf,
+    /// Emit the output in text format instead of the binary format
+    #[structopt(long = "text")]
+    text: bool,
+    /// Functions with a max instrumentation count below this are "cold".
+    /// Derived from the instrumentation profile summary when unset.
+    #[structopt(long = "instr-prof-cold-threshold")]
+    instr_prof_cold_threshold: Option<u64>,
+    /// Sample-profile total-sample count at or above which a function is "hot"
+    #[structopt(long = "sample-profile-hot-threshold", default_value = "1")]
+    sample_profile_hot_threshold: u64,
+    /// Drop, rather than scale up, cold functions whose zero-counter
+    /// fraction exceeds this
+    #[structopt(long = "zero-counter-threshold", default_value = "0.7")]
+    zero_counter_threshold: f64,
+    /// Functions with fewer counters than this are left untouched
+    #[structopt(long = "suppl-min-size-threshold", default_value = "3")]
+    suppl_min_size_threshold: usize,
+}
+
+impl SupplementCommand {
+    pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut profile = parse(&self.instr_profile)?;
+        let sample = sample_profile::parse(&self.sample_profile)?;
+
+        let options = SupplementOptions {
+            instr_prof_cold_threshold: self.instr_prof_cold_threshold,
+            sample_profile_hot_threshold: self.sample_profile_hot_threshold,
+            zero_counter_threshold: self.zero_counter_threshold,
+            suppl_min_size_threshold: self.suppl_min_size_threshold,
+        };
+        supplement_instr_with_sample(&mut profile, &sample, &options);
+
+        let format = if self.text { OutputFormat::Text } else { OutputFormat::Binary };
+        let mut file = std::fs::File::create(&self.output)?;
+        write_profile(&profile, format, &mut file)?;
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, StructOpt)]
+pub struct OrderCommand {
+    /// Input profile carrying temporal profiling traces
+    #[structopt(long = "input", short = "i")]
+    input: PathBuf,
+    /// Output file for the ordered symbol list (one per line); stdout if unset
+    #[structopt(long = "output", short = "o")]
+    output: Option<PathBuf>,
+    /// Stop recursively splitting a group once it has this many or fewer functions
+    #[structopt(long = "leaf-size", default_value = "4")]
+    leaf_size: usize,
+}
+
+impl OrderCommand {
+    pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let profile = parse(&self.input)?;
+        let options = OrderOptions {
+            leaf_size: self.leaf_size,
+            ..OrderOptions::default()
+        };
+        // `parse` doesn't know about temporal profiling traces, so
+        // `profile.temporal_prof_traces` is always empty in practice; read
+        // them straight out of the indexed profile file instead.
+        let traces = read_temporal_prof_traces(&self.input)?;
+        let order = balanced_partition_order(&traces, &options);
+
+        let symbols: Vec<String> = order
+            .iter()
+            .map(|hash| {
+                profile
+                    .symtab
+                    .names
+                    .get(hash)
+                    .cloned()
+                    .unwrap_or_else(|| format!("0x{:x}", hash))
+            })
+            .collect();
+
+        let output = symbols.join("\n");
+        if let Some(path) = &self.output {
+            std::fs::write(path, output)?;
+        } else {
+            println!("{}", output);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, StructOpt)]
 pub struct Opts {
     #[structopt(subcommand)]
     cmd: Command,
@@ -124,12 +462,21 @@ fn check_function(name: Option<&String>, pattern: Option<&String>) -> bool {
 
 impl ShowCommand {
     pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let profile = parse(&self.input)?;
+        let mut profile = parse(&self.input)?;
+        if let Some(binary_file) = &self.binary_file {
+            let raw_counters: Vec<u64> = profile
+                .records
+                .iter()
+                .flat_map(|r| r.record.counts.iter().copied())
+                .collect();
+            profile = correlate_profile(&raw_counters, binary_file)?;
+        }
         let mut summary = ProfileSummary::new();
         let mut stats = vec![ValueSiteStats::default(); ValueKind::len()];
 
         let is_ir_instr = profile.is_ir_level_profile();
         let mut shown_funcs = 0;
+        let mut hot_functions: Vec<(String, u64)> = Vec::new();
         for func in &profile.records {
             if func.name.is_none() || func.hash.is_none() {
                 continue;
@@ -137,6 +484,12 @@ impl ShowCommand {
             if is_ir_instr && func.has_cs_flag() != self.showcs {
                 continue;
             }
+            let max_count = if is_ir_instr {
+                func.record.counts.iter().copied().max().unwrap_or(0)
+            } else {
+                func.record.counts.first().copied().unwrap_or(0)
+            };
+            hot_functions.push((func.name.clone().unwrap(), max_count));
             let show =
                 self.all_functions || check_function(func.name.as_ref(), self.function.as_ref());
             summary.add_record(&func.record);
@@ -198,7 +551,27 @@ impl ShowCommand {
             "Maximum internal block count: {}",
             summary.max_internal_block_count()
         );
-        if let Some(_topn) = self.topn {}
+        if self.topn.is_some() || self.show_hot_fn_list {
+            let value_cutoff = self.value_cutoff.unwrap_or(0);
+            let mut hot_functions: Vec<(String, u64)> = hot_functions
+                .into_iter()
+                .filter(|&(_, count)| {
+                    if self.only_list_below {
+                        (count as usize) < value_cutoff
+                    } else {
+                        (count as usize) >= value_cutoff
+                    }
+                })
+                .collect();
+            hot_functions.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+            if let Some(topn) = self.topn {
+                hot_functions.truncate(topn);
+            }
+            println!("Functions with the largest internal block counts:");
+            for (name, count) in hot_functions {
+                println!("  {}: {}", name, count);
+            }
+        }
 
         if self.ic_targets && shown_funcs > 0 {
             println!("Statistics for indirect call sites profile:");
@@ -211,8 +584,28 @@ impl ShowCommand {
         }
 
         if self.show_detailed_summary {
-            println!("Total number of blocks: ?");
-            println!("Total count: ?");
+            println!("Total number of blocks: {}", summary.num_counts());
+            println!("Total count: {}", summary.total_count());
+            let cutoffs = if self.detailed_summary_cutoffs.is_empty() {
+                DEFAULT_CUTOFFS.to_vec()
+            } else {
+                self.detailed_summary_cutoffs.clone()
+            };
+            for entry in summary.detailed_summary(&cutoffs).entries {
+                println!(
+                    "{:.4}% of counts are from blocks with count >= {}",
+                    entry.cutoff as f64 / 10_000.0,
+                    entry.min_count
+                );
+            }
+        }
+
+        if let Some(output) = &self.output {
+            let format = if self.text { OutputFormat::Text } else { OutputFormat::Binary };
+            let mut file = std::fs::File::create(output)?;
+            write_profile(&profile, format, &mut file)?;
+        } else if self.text {
+            write_profile(&profile, OutputFormat::Text, &mut std::io::stdout())?;
         }
         Ok(())
     }
@@ -222,8 +615,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opts = Opts::from_args();
     match opts.cmd {
         Command::Show { show } => show.run(),
-        _ => {
-            panic!("Unsupported command");
-        }
+        Command::Merge { merge } => merge.run(),
+        Command::Overlap { overlap } => overlap.run(),
+        Command::Supplement { supplement } => supplement.run(),
+        Command::Order { order } => order.run(),
     }
 }